@@ -0,0 +1,261 @@
+//! Scans MER/APA files directly out of container archives (7z, zip, tar) without
+//! extracting them to disk first, by reading each entry into memory and handing the
+//! resulting buffer to `cfb` the same way a normal file would be.
+
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use sevenz_rust::Password;
+
+use crate::{protected_from_cfb, version_from_cfb, FileVersion, FtvFileError};
+
+/// Identifies which container format an archive of runtime files is stored in.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ArchiveKind {
+    SevenZip,
+    Zip,
+    Tar,
+}
+
+impl ArchiveKind {
+    /// Detects the archive format from the file extension, falling back to sniffing the
+    /// leading bytes for formats whose extension doesn't match (e.g. a renamed `.7z`).
+    /// Only 7z and zip archives are recognized by their leading bytes; a tar archive is
+    /// recognized by its `.tar` extension only.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file can't be opened, or if neither the extension nor the
+    /// header bytes match a supported archive format.
+    pub fn detect<P: AsRef<Path>>(archive: P) -> Result<ArchiveKind, FtvFileError> {
+        let archive = archive.as_ref();
+
+        match archive
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("7z") => return Ok(ArchiveKind::SevenZip),
+            Some("zip") => return Ok(ArchiveKind::Zip),
+            Some("tar") => return Ok(ArchiveKind::Tar),
+            _ => {}
+        }
+
+        let mut header = [0u8; 6];
+        let mut file = std::fs::File::open(archive)?;
+        let read = file.read(&mut header)?;
+
+        if read >= 6 && header == [0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C] {
+            Ok(ArchiveKind::SevenZip)
+        } else if read >= 4 && header[..4] == [0x50, 0x4B, 0x03, 0x04] {
+            Ok(ArchiveKind::Zip)
+        } else {
+            Err(FtvFileError::UnknownArchiveFormat(archive.to_path_buf()))
+        }
+    }
+}
+
+/// Returns true if `name` looks like an MER or APA runtime file based on its extension.
+fn is_runtime_file(name: &str) -> bool {
+    name.rsplit('.').next().map_or(false, |ext| {
+        ext.eq_ignore_ascii_case("mer") || ext.eq_ignore_ascii_case("apa")
+    })
+}
+
+/// Reads every MER/APA entry out of `archive` into memory, returning each entry's path
+/// (relative to the archive root) alongside its raw bytes.
+///
+/// # Errors
+///
+/// Will return `Err` if the archive can't be opened or its format can't be determined.
+fn read_runtime_entries<P: AsRef<Path>>(
+    archive: P,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, FtvFileError> {
+    let archive = archive.as_ref();
+    let kind = ArchiveKind::detect(archive)?;
+    let mut entries = Vec::new();
+
+    match kind {
+        ArchiveKind::SevenZip => {
+            let mut reader = sevenz_rust::SevenZReader::open(archive, Password::empty())?;
+            reader.for_each_entries(|entry, reader| {
+                let name = entry.name();
+                if !entry.is_directory() && is_runtime_file(name) {
+                    let mut buffer = Vec::new();
+                    reader.read_to_end(&mut buffer)?;
+                    entries.push((PathBuf::from(name), buffer));
+                } else {
+                    let mut sink = std::io::sink();
+                    std::io::copy(reader, &mut sink)?;
+                }
+                Ok(true)
+            })?;
+        }
+        ArchiveKind::Zip => {
+            let file = std::fs::File::open(archive)?;
+            let mut zip = zip::ZipArchive::new(file)?;
+            for i in 0..zip.len() {
+                let mut zip_entry = zip.by_index(i)?;
+                if zip_entry.is_file() && is_runtime_file(zip_entry.name()) {
+                    let path = PathBuf::from(zip_entry.name());
+                    let mut buffer = Vec::new();
+                    zip_entry.read_to_end(&mut buffer)?;
+                    entries.push((path, buffer));
+                }
+            }
+        }
+        ArchiveKind::Tar => {
+            let file = std::fs::File::open(archive)?;
+            let mut tar = tar::Archive::new(file);
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                let path = entry.path()?.into_owned();
+                if path
+                    .file_name()
+                    .and_then(std::ffi::OsStr::to_str)
+                    .is_some_and(is_runtime_file)
+                {
+                    let mut buffer = Vec::new();
+                    entry.read_to_end(&mut buffer)?;
+                    entries.push((path, buffer));
+                }
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+// https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
+/// Scans every MER/APA entry inside a 7z/zip/tar archive and returns its `FactoryTalk` View
+/// file version, without extracting the archive to disk.
+///
+/// # Arguments
+///
+/// * `archive` - A path to the container archive to scan
+///
+/// # Examples
+///
+/// ```no_run
+/// use ab_versions::get_versions_in_archive;
+/// let versions = get_versions_in_archive("./runtime_bundle.7z").unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the archive itself can't be opened or its format isn't recognized.
+pub fn get_versions_in_archive<P: AsRef<Path>>(
+    archive: P,
+) -> Result<Vec<(PathBuf, Result<FileVersion, FtvFileError>)>, FtvFileError> {
+    let entries = read_runtime_entries(archive)?;
+
+    Ok(entries
+        .into_par_iter()
+        .map(|(path, bytes)| {
+            let result = cfb::CompoundFile::open(Cursor::new(bytes))
+                .map_err(FtvFileError::from)
+                .and_then(|mut file| version_from_cfb(&mut file));
+            (path, result)
+        })
+        .collect())
+}
+
+// https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
+/// Scans every MER/APA entry inside a 7z/zip/tar archive and returns whether it's protected,
+/// without extracting the archive to disk.
+///
+/// # Arguments
+///
+/// * `archive` - A path to the container archive to scan
+///
+/// # Examples
+///
+/// ```no_run
+/// use ab_versions::are_protected_in_archive;
+/// let protection = are_protected_in_archive("./runtime_bundle.7z").unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the archive itself can't be opened or its format isn't recognized.
+pub fn are_protected_in_archive<P: AsRef<Path>>(
+    archive: P,
+) -> Result<Vec<(PathBuf, Result<bool, FtvFileError>)>, FtvFileError> {
+    let entries = read_runtime_entries(archive)?;
+
+    Ok(entries
+        .into_par_iter()
+        .map(|(path, bytes)| {
+            let result = cfb::CompoundFile::open(Cursor::new(bytes))
+                .map_err(FtvFileError::from)
+                .and_then(|mut file| protected_from_cfb(&mut file));
+            (path, result)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_runtime_file_matches_mer_and_apa_case_insensitively() {
+        assert!(is_runtime_file("Runtime.MER"));
+        assert!(is_runtime_file("project.apa"));
+        assert!(!is_runtime_file("readme.txt"));
+        assert!(!is_runtime_file("no_extension"));
+    }
+
+    #[test]
+    fn detect_uses_extension_without_touching_the_filesystem() {
+        // These paths don't exist on disk; detection by extension must short-circuit
+        // before any attempt to open the file.
+        assert_eq!(
+            ArchiveKind::detect("bundle.7z").unwrap(),
+            ArchiveKind::SevenZip
+        );
+        assert_eq!(
+            ArchiveKind::detect("bundle.ZIP").unwrap(),
+            ArchiveKind::Zip
+        );
+        assert_eq!(
+            ArchiveKind::detect("bundle.tar").unwrap(),
+            ArchiveKind::Tar
+        );
+    }
+
+    #[test]
+    fn detect_sniffs_magic_bytes_when_extension_is_unrecognized() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let mut seven_zip_file = NamedTempFile::new().unwrap();
+        seven_zip_file
+            .write_all(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C])
+            .unwrap();
+        assert_eq!(
+            ArchiveKind::detect(seven_zip_file.path()).unwrap(),
+            ArchiveKind::SevenZip
+        );
+
+        let mut zip_file = NamedTempFile::new().unwrap();
+        zip_file.write_all(&[0x50, 0x4B, 0x03, 0x04]).unwrap();
+        assert_eq!(ArchiveKind::detect(zip_file.path()).unwrap(), ArchiveKind::Zip);
+    }
+
+    #[test]
+    fn detect_errors_on_unrecognized_file() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"not an archive").unwrap();
+
+        assert!(matches!(
+            ArchiveKind::detect(file.path()),
+            Err(FtvFileError::UnknownArchiveFormat(_))
+        ));
+    }
+}
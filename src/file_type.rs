@@ -0,0 +1,145 @@
+//! Classifies a `FactoryTalk` View file as an MER runtime file or an APA application
+//! archive before any stream is read, so callers (and `strip_protection`) can gate on it
+//! instead of guessing from the file extension.
+
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::FtvFileError;
+
+/// The OLE/Compound File Binary signature every MER and APA file starts with.
+const CFB_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Distinguishes the two `FactoryTalk` View file kinds this crate understands.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum FileType {
+    /// A compiled Machine Edition runtime file (`.mer`).
+    MerRuntime,
+    /// A project application archive (`.apa`).
+    ApaArchive,
+    /// A valid compound file whose contents don't match either known layout.
+    Unknown,
+}
+
+/// Checks the leading bytes of `path` against the OLE/Compound File signature, before
+/// `cfb` is asked to parse the rest of the file.
+///
+/// # Errors
+///
+/// Will return `Err` if the file can't be read, or if it doesn't start with a valid
+/// OLE/Compound File signature.
+pub(crate) fn ensure_compound_file<P: AsRef<Path>>(path: P) -> Result<(), FtvFileError> {
+    let path = path.as_ref();
+    let mut header = [0u8; 8];
+    let mut file = std::fs::File::open(path)?;
+
+    file.read_exact(&mut header)
+        .map_err(|_| FtvFileError::NotCompoundFile(path.to_path_buf()))?;
+
+    if header == CFB_SIGNATURE {
+        Ok(())
+    } else {
+        Err(FtvFileError::NotCompoundFile(path.to_path_buf()))
+    }
+}
+
+/// Classifies an already-open compound file by which streams it contains.
+///
+/// MER runtime files carry a `.med` runtime stream; APA archives don't, but do carry the
+/// `VERSION_INFORMATION`/`FILE_PROTECTION` streams this crate already reads from project
+/// archives. Anything else is reported as `Unknown` rather than guessed at.
+pub(crate) fn classify<F: Read + Seek>(file: &mut cfb::CompoundFile<F>) -> FileType {
+    let has_med_stream = file.walk().any(|entry| {
+        entry.is_stream() && entry.name().to_ascii_lowercase().contains(".med")
+    });
+
+    if has_med_stream {
+        FileType::MerRuntime
+    } else if file.exists("/VERSION_INFORMATION") || file.exists("/FILE_PROTECTION") {
+        FileType::ApaArchive
+    } else {
+        FileType::Unknown
+    }
+}
+
+// https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
+/// Determines whether a file is an MER runtime file, an APA archive, or neither.
+///
+/// # Arguments
+///
+/// * `path` - A path to the file to be checked
+///
+/// # Examples
+///
+/// ```no_run
+/// use ab_versions::detect_type;
+/// let file_type = detect_type(&path_to_file).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if there is an error trying to access the file, or if it isn't a
+/// valid OLE/Compound File container.
+pub fn detect_type<P: AsRef<Path>>(path: P) -> Result<FileType, FtvFileError> {
+    let path = path.as_ref();
+    ensure_compound_file(path)?;
+    let mut file = cfb::open(path)?;
+    Ok(classify(&mut file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    fn new_in_memory_cfb() -> cfb::CompoundFile<Cursor<Vec<u8>>> {
+        cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap()
+    }
+
+    #[test]
+    fn classify_detects_mer_runtime_by_med_stream() {
+        let mut file = new_in_memory_cfb();
+        file.create_stream("/RUNTIME.med").unwrap();
+
+        assert_eq!(classify(&mut file), FileType::MerRuntime);
+    }
+
+    #[test]
+    fn classify_detects_apa_archive_by_version_stream() {
+        let mut file = new_in_memory_cfb();
+        file.create_stream("/VERSION_INFORMATION").unwrap();
+
+        assert_eq!(classify(&mut file), FileType::ApaArchive);
+    }
+
+    #[test]
+    fn classify_reports_unknown_for_unrelated_streams() {
+        let mut file = new_in_memory_cfb();
+        file.create_stream("/SOMETHING_ELSE").unwrap();
+
+        assert_eq!(classify(&mut file), FileType::Unknown);
+    }
+
+    #[test]
+    fn ensure_compound_file_rejects_non_cfb_files() {
+        use tempfile::NamedTempFile;
+
+        let mut garbage = NamedTempFile::new().unwrap();
+        garbage.write_all(b"not a compound file").unwrap();
+
+        assert!(matches!(
+            ensure_compound_file(garbage.path()),
+            Err(FtvFileError::NotCompoundFile(_))
+        ));
+    }
+
+    #[test]
+    fn ensure_compound_file_accepts_valid_signature() {
+        use tempfile::NamedTempFile;
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&CFB_SIGNATURE).unwrap();
+
+        assert!(ensure_compound_file(file.path()).is_ok());
+    }
+}
@@ -7,18 +7,41 @@
 #![deny(clippy::all)]
 #![deny(clippy::pedantic)]
 
-use std::io::{Read, Write};
-use std::path::Path;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
 use std::{fmt, fmt::Display};
 use rayon::prelude::*;
 use thiserror::Error;
 
+mod archive;
+mod file_type;
+mod parse;
+mod scan;
+
+pub use archive::{get_versions_in_archive, are_protected_in_archive, ArchiveKind};
+pub use file_type::{detect_type, FileType};
+pub use parse::ProtectionState;
+pub use scan::{scan, ScanError, ScanRecord, ScanReport};
+
 #[derive(Error, Debug)]
 pub enum FTypeError {
     #[error("No version information was found in the file")]
     NoVersion,
-    #[error("Version information appears to be invalid")]
-    InvalidVersion,
+    #[error("The VERSION_INFORMATION stream only contained {len} byte(s), need at least 3")]
+    TruncatedVersion { len: usize },
+    #[error("The VERSION_INFORMATION stream was {len} bytes long, longer than the expected 3")]
+    UnexpectedVersionLayout { len: usize },
+    #[error("The FILE_PROTECTION stream's {0:?} didn't match the unlocked or never-restore pattern")]
+    UnrecognizedProtectionPattern(Vec<u8>),
+    #[error("The file could not be classified as an MER runtime file or an APA archive")]
+    UnrecognizedFileType,
+    #[error("File version {found} predates the minimum supported version {minimum} and doesn't carry restore information")]
+    UnsupportedVersion {
+        found: FileVersion,
+        minimum: FileVersion,
+    },
+    #[error("Setting password protection requires a known password hash, which this crate cannot compute")]
+    PasswordProtectionUnsupported,
 }
 
 #[derive(Error, Debug)]
@@ -28,10 +51,25 @@ pub enum FtvFileError {
 
     #[error("The file does not appear to be a valid FactoryTalk View ME File: {0:?}")]
     FileTypeError(#[from] FTypeError),
+
+    #[error("{0:?} is not a recognized archive format (expected .7z, .zip, or .tar)")]
+    UnknownArchiveFormat(PathBuf),
+
+    #[error("{0:?} does not appear to be a valid OLE/Compound File container")]
+    NotCompoundFile(PathBuf),
+
+    #[error("There was an error while trying to read a zip archive")]
+    ZipError(#[from] zip::result::ZipError),
+
+    #[error("There was an error while trying to read a 7z archive")]
+    SevenZError(#[from] sevenz_rust::Error),
 }
 
 /// Holds the version number of the file.
-#[derive(Debug, Default, Eq, PartialEq, Clone)]
+///
+/// `FileVersion` orders first by `major_rev`, then by `minor_rev`, since that's how
+/// `FactoryTalk` View itself treats version precedence.
+#[derive(Debug, Default, Eq, PartialEq, PartialOrd, Ord, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FileVersion {
     /// Major Revision Number
     major_rev: u8,
@@ -45,6 +83,20 @@ impl Display for FileVersion {
     }
 }
 
+/// Known `major.minor` pairs mapped to the marketing/product name `FactoryTalk` View used
+/// for that release, so callers can show something more meaningful than raw revision bytes.
+const PRODUCT_NAMES: &[((u8, u8), &str)] = &[
+    ((5, 0), "FactoryTalk View ME 5.00"),
+    ((6, 0), "FactoryTalk View ME 6.00"),
+    ((7, 0), "FactoryTalk View ME 7.00"),
+    ((8, 0), "FactoryTalk View ME 8.00"),
+    ((9, 0), "FactoryTalk View ME 9.00"),
+    ((10, 0), "FactoryTalk View ME 10.00"),
+    ((11, 0), "FactoryTalk View ME 11.00"),
+    ((12, 0), "FactoryTalk View ME 12.00"),
+    ((13, 0), "FactoryTalk View ME 13.00"),
+];
+
 impl FileVersion {
     pub fn is_old(&self) -> bool {
         self.major_rev < 5
@@ -53,6 +105,25 @@ impl FileVersion {
     pub fn is_restorable(&self) -> bool {
         self.major_rev >= 4
     }
+
+    /// Returns true if a Studio of version `studio` could open/restore a file of `self`'s version.
+    ///
+    /// Like semver's caret compatibility, the Studio version acts as a ceiling: it can open
+    /// anything it's newer than or equal to, but never a file saved by a newer Studio.
+    #[must_use]
+    pub fn is_compatible_with(&self, studio: &FileVersion) -> bool {
+        self <= studio
+    }
+
+    /// Looks up the marketing/product version string for this file's `major.minor` pair,
+    /// e.g. "FactoryTalk View ME 12.00". Returns `None` for revisions not in the known table.
+    #[must_use]
+    pub fn product_name(&self) -> Option<&'static str> {
+        PRODUCT_NAMES
+            .iter()
+            .find(|((major, minor), _)| *major == self.major_rev && *minor == self.minor_rev)
+            .map(|(_, name)| *name)
+    }
 }
 
 
@@ -77,7 +148,15 @@ impl FileVersion {
 // or the file is invalid.
 pub fn get_version<P: AsRef<Path>>(filename: &P) -> Result<FileVersion, FtvFileError> {
     let mut file = cfb::open(filename)?;
+    version_from_cfb(&mut file)
+}
 
+/// Reads the `FactoryTalk` View file version out of an already-open compound file, regardless
+/// of whether it's backed by a file on disk or an in-memory buffer (e.g. an entry read out of
+/// an archive). Shared by [`get_version`] and the archive-scanning functions in [`archive`].
+pub(crate) fn version_from_cfb<F: Read + Seek>(
+    file: &mut cfb::CompoundFile<F>,
+) -> Result<FileVersion, FtvFileError> {
     let version_data = {
         let mut stream =
             file.open_stream("/VERSION_INFORMATION")
@@ -92,14 +171,7 @@ pub fn get_version<P: AsRef<Path>>(filename: &P) -> Result<FileVersion, FtvFileE
         buffer
     };
 
-    if version_data.len() == 3 {
-        Ok(FileVersion {
-            major_rev: version_data[1],
-            minor_rev: version_data[2],
-        })
-    } else {
-        Err(FTypeError::InvalidVersion.into())
-    }
+    Ok(parse::parse_version(&version_data)?)
 }
 
 // https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
@@ -152,24 +224,51 @@ pub fn get_versions<P>(files: &[P]) -> Vec<Result<FileVersion, FtvFileError>>
 // or the file is invalid.
 pub fn is_protected<P: AsRef<Path>>(path: &P) -> Result<bool, FtvFileError> {
     let mut file = cfb::open(path)?;
+    protected_from_cfb(&mut file)
+}
+
+/// Reads protection state out of an already-open compound file. Shared by [`is_protected`]
+/// and the archive-scanning functions in [`archive`].
+pub(crate) fn protected_from_cfb<F: Read + Seek>(
+    file: &mut cfb::CompoundFile<F>,
+) -> Result<bool, FtvFileError> {
+    Ok(protection_state_from_cfb(file)? != ProtectionState::Unlocked)
+}
+
+// https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
+/// Returns the detailed [`ProtectionState`] of a `FactoryTalk` View file (APA or MER), rather
+/// than just whether it's protected.
+///
+/// # Arguments
+///
+/// * `path` - A path to the file to be checked
+///
+/// # Examples
+///
+/// ```
+/// use ab_versions::protection_state;
+/// let state = protection_state(&path_to_file).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err`  if there is an error trying to access the file,
+// or the file is invalid.
+pub fn protection_state<P: AsRef<Path>>(path: &P) -> Result<ProtectionState, FtvFileError> {
+    let mut file = cfb::open(path)?;
+    protection_state_from_cfb(&mut file)
+}
 
+/// Reads detailed protection state out of an already-open compound file. Shared by
+/// [`protection_state`] and the archive-scanning functions in [`archive`].
+pub(crate) fn protection_state_from_cfb<F: Read + Seek>(
+    file: &mut cfb::CompoundFile<F>,
+) -> Result<ProtectionState, FtvFileError> {
     let mut prot_stream = file.open_stream("/FILE_PROTECTION")?;
+    let mut buf = Vec::with_capacity(prot_stream.len() as usize);
+    prot_stream.read_to_end(&mut buf)?;
 
-    // I'm not quite sure exactly what the contents of the file is if it is unprotected
-    // So far has always been 7 bytes, and the the second byte has always been a 3,
-    // and the rest have been 0. If it's password protected it's always been greater than
-    // 7 bytes. I assume it's some hashed form of the password.
-    // The exception here is if when an MER is exported with the "Never Convert" option selected
-    // the bytes pattern seems to always be: [00, 03, 00, 01, 00, 00, 00], pretty similar to the
-    // unlocked bytes, but with the 4th byte set to 1.
-
-    Ok(if prot_stream.len() == 7 {
-        let mut buf: Vec<u8> = Vec::with_capacity(7);
-        prot_stream.read_to_end(&mut buf)?;
-        buf == [0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00]
-    } else {
-        prot_stream.len() > 7
-    })
+    Ok(parse::parse_protection(&buf)?)
 }
 
 // https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
@@ -241,14 +340,31 @@ pub fn strip_protection<P: AsRef<Path>>(path: P) -> Result<(), FtvFileError> {
     //no clue if this actually works on anything < v4 since I have nothing to test against
 
 
-    let mut file = cfb::open_rw(&path)?;
+    // Gate on type and version up front: writing a FILE_PROTECTION stream into a file that
+    // isn't recognized as MER/APA, or that predates the files carrying restore information
+    // at all, wouldn't actually make it restorable.
+    if detect_type(&path)? == FileType::Unknown {
+        return Err(FTypeError::UnrecognizedFileType.into());
+    }
 
     let version = get_version(&path)?;
 
+    if !version.is_restorable() {
+        return Err(FTypeError::UnsupportedVersion {
+            found: version,
+            minimum: FileVersion {
+                major_rev: 4,
+                minor_rev: 0,
+            },
+        }
+        .into());
+    }
+
+    let mut file = cfb::open_rw(&path)?;
+
     if version.major_rev < 5 {
         //if version < 5 use other method, to create FILE_PROTECTION
         //and set the file_version to 5.10
-        //wonder if I should be checking for a .med stream to verify it's an MER here?
         let mut fp_stream = file.create_new_stream("/FILE_PROTECTION")?;
         fp_stream.set_len(7)?;
         fp_stream.write_all(&[0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00])?;
@@ -306,6 +422,92 @@ pub fn strip_protections<P>(files: &[P]) -> Result<(), FtvFileError>
     }).collect()
 }
 
+/// The protection `set_protection` should apply to a file's `FILE_PROTECTION` stream.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ProtectionMode {
+    /// Write the unlocked pattern, the same one `strip_protection` writes.
+    Unlocked,
+    /// Write the "Never Convert" pattern, so an MER can no longer be restored to a project.
+    NeverRestore,
+    /// Password protection. Best-effort: this crate doesn't know how `FactoryTalk` View
+    /// hashes passwords, so this mode always fails.
+    PasswordProtected,
+}
+
+// https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
+/// Applies protection to a `FactoryTalk` View MER or APA file, the inverse of
+/// `strip_protection`. Creates the `FILE_PROTECTION` stream if it doesn't already exist.
+///
+/// # Arguments
+///
+/// * `path` - A path to the file to be modified
+/// * `mode` - The protection to apply
+///
+/// # Examples
+///
+/// ```
+/// use ab_versions::{set_protection, ProtectionMode};
+/// set_protection(&path_to_file, ProtectionMode::NeverRestore).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err`  if there is an error trying to access the file, or if `mode` is
+/// `ProtectionMode::PasswordProtected`, which this crate can't compute a hash for.
+pub fn set_protection<P: AsRef<Path>>(path: P, mode: ProtectionMode) -> Result<(), FtvFileError> {
+    let pattern = match mode {
+        ProtectionMode::Unlocked => parse::UNLOCKED_PATTERN,
+        ProtectionMode::NeverRestore => parse::NEVER_RESTORE_PATTERN,
+        ProtectionMode::PasswordProtected => {
+            return Err(FTypeError::PasswordProtectionUnsupported.into())
+        }
+    };
+
+    let mut file = cfb::open_rw(&path)?;
+
+    let mut stream = match file.open_stream("/FILE_PROTECTION") {
+        Ok(stream) => stream,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            file.create_new_stream("/FILE_PROTECTION")?
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    stream.set_len(7)?;
+    stream.write_all(&pattern)?;
+
+    Ok(())
+}
+
+// https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
+/// Applies protection to a batch of `FactoryTalk` View MER or APA files in parallel.
+///
+/// # Arguments
+///
+/// * `files` - A slice of paths to the files to be modified
+/// * `mode` - The protection to apply to each file
+///
+/// # Examples
+///
+/// ```
+/// use ab_versions::{set_protections, ProtectionMode};
+/// set_protections(&paths_to_files, ProtectionMode::Unlocked).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err`  if there is an error trying to access the file, or if `mode` is
+/// `ProtectionMode::PasswordProtected`, which this crate can't compute a hash for.
+//this parrallel version is mainly for the python bindings to take advantage
+//or the parrallelization
+pub fn set_protections<P>(files: &[P], mode: ProtectionMode) -> Result<(), FtvFileError>
+    where P: AsRef<Path> + Sync
+{
+    files.as_parallel_slice().par_iter().map(|file| -> Result<(), FtvFileError> {
+        set_protection(file, mode)
+    }).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use nom::{
@@ -417,4 +619,111 @@ mod tests {
     fn never_file() {
         process_archive("./test_files/Never.7z", &FileState::Never);
     }
+
+    #[test]
+    fn set_protection_round_trips_with_protection_state() {
+        use super::*;
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().expect("failed to create a temporary file");
+        drop(cfb::create(file.path()).expect("failed to create a compound file at the temp path"));
+
+        set_protection(file.path(), ProtectionMode::NeverRestore).unwrap();
+        assert_eq!(
+            protection_state(&file.path()).unwrap(),
+            ProtectionState::NeverRestore
+        );
+        assert!(is_protected(&file.path()).unwrap());
+
+        set_protection(file.path(), ProtectionMode::Unlocked).unwrap();
+        assert_eq!(
+            protection_state(&file.path()).unwrap(),
+            ProtectionState::Unlocked
+        );
+        assert!(!is_protected(&file.path()).unwrap());
+    }
+
+    #[test]
+    fn set_protection_rejects_password_protected_mode() {
+        use super::*;
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().expect("failed to create a temporary file");
+        drop(cfb::create(file.path()).expect("failed to create a compound file at the temp path"));
+
+        let err = set_protection(file.path(), ProtectionMode::PasswordProtected).unwrap_err();
+        assert!(matches!(
+            err,
+            FtvFileError::FileTypeError(FTypeError::PasswordProtectionUnsupported)
+        ));
+    }
+
+    #[test]
+    fn set_protections_applies_mode_to_every_file() {
+        use super::*;
+        use tempfile::NamedTempFile;
+
+        let files: Vec<_> = (0..2)
+            .map(|_| {
+                let file = NamedTempFile::new().expect("failed to create a temporary file");
+                drop(
+                    cfb::create(file.path())
+                        .expect("failed to create a compound file at the temp path"),
+                );
+                file
+            })
+            .collect();
+        let paths: Vec<_> = files.iter().map(tempfile::NamedTempFile::path).collect();
+
+        set_protections(&paths, ProtectionMode::NeverRestore).unwrap();
+
+        for path in &paths {
+            assert_eq!(protection_state(path).unwrap(), ProtectionState::NeverRestore);
+        }
+    }
+
+    #[test]
+    fn is_compatible_with_allows_equal_and_older_file_versions() {
+        use super::*;
+
+        let studio = FileVersion {
+            major_rev: 12,
+            minor_rev: 5,
+        };
+
+        assert!(studio.is_compatible_with(&studio));
+        assert!(FileVersion { major_rev: 11, minor_rev: 9 }.is_compatible_with(&studio));
+        assert!(FileVersion { major_rev: 12, minor_rev: 0 }.is_compatible_with(&studio));
+    }
+
+    #[test]
+    fn is_compatible_with_rejects_newer_file_versions() {
+        use super::*;
+
+        let studio = FileVersion {
+            major_rev: 12,
+            minor_rev: 5,
+        };
+
+        assert!(!FileVersion { major_rev: 13, minor_rev: 0 }.is_compatible_with(&studio));
+        assert!(!FileVersion { major_rev: 12, minor_rev: 6 }.is_compatible_with(&studio));
+    }
+
+    #[test]
+    fn product_name_hits_known_version_and_misses_unknown_one() {
+        use super::*;
+
+        assert_eq!(
+            FileVersion { major_rev: 12, minor_rev: 0 }.product_name(),
+            Some("FactoryTalk View ME 12.00")
+        );
+        assert_eq!(
+            FileVersion { major_rev: 12, minor_rev: 3 }.product_name(),
+            None
+        );
+        assert_eq!(
+            FileVersion { major_rev: 2, minor_rev: 0 }.product_name(),
+            None
+        );
+    }
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+//! `nom` parsers for the two streams this crate reads: `VERSION_INFORMATION` and
+//! `FILE_PROTECTION`. Kept separate from the stream-reading code in `lib.rs` so the byte
+//! layouts are described in one place instead of as inline indexing.
+
+use nom::{bytes::complete::tag, combinator::value, number::complete::u8 as any_u8, IResult};
+
+use crate::{FTypeError, FileVersion};
+
+/// The 7-byte pattern `FactoryTalk` View writes into `FILE_PROTECTION` for an unlocked file.
+pub(crate) const UNLOCKED_PATTERN: [u8; 7] = [0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+/// The 7-byte pattern written when an MER was exported with "Never Convert" selected.
+pub(crate) const NEVER_RESTORE_PATTERN: [u8; 7] = [0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00];
+
+/// The protection state recorded in a file's `FILE_PROTECTION` stream.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum ProtectionState {
+    /// The file can be opened/restored without a password.
+    Unlocked,
+    /// The MER was exported with "Never Convert" and can't be restored to a project.
+    NeverRestore,
+    /// The stream holds what appears to be a hashed password.
+    PasswordProtected,
+}
+
+/// Parses the leading tag byte, `major_rev`, and `minor_rev` out of a `VERSION_INFORMATION`
+/// stream. Doesn't care what comes after; callers check for trailing bytes themselves so
+/// they can tell "too short" and "too long" apart.
+fn version_fields(input: &[u8]) -> IResult<&[u8], (u8, u8, u8)> {
+    let (input, tag_byte) = any_u8(input)?;
+    let (input, major_rev) = any_u8(input)?;
+    let (input, minor_rev) = any_u8(input)?;
+    Ok((input, (tag_byte, major_rev, minor_rev)))
+}
+
+/// Parses the contents of a `VERSION_INFORMATION` stream into a [`FileVersion`].
+///
+/// # Errors
+///
+/// Returns [`FTypeError::TruncatedVersion`] if fewer than three bytes are present, or
+/// [`FTypeError::UnexpectedVersionLayout`] if more bytes follow the three expected ones.
+pub(crate) fn parse_version(data: &[u8]) -> Result<FileVersion, FTypeError> {
+    let (remaining, (_tag, major_rev, minor_rev)) =
+        version_fields(data).map_err(|_| FTypeError::TruncatedVersion { len: data.len() })?;
+
+    if remaining.is_empty() {
+        Ok(FileVersion {
+            major_rev,
+            minor_rev,
+        })
+    } else {
+        Err(FTypeError::UnexpectedVersionLayout { len: data.len() })
+    }
+}
+
+/// Parses the contents of a `FILE_PROTECTION` stream into a [`ProtectionState`].
+///
+/// Note this is stricter than the old bare-`bool` `is_protected` used to be: a stream of 7
+/// bytes or fewer that doesn't match the unlocked or never-restore pattern now returns
+/// `Err` instead of being silently treated as unprotected.
+///
+/// # Errors
+///
+/// Returns [`FTypeError::UnrecognizedProtectionPattern`] if the stream is 7 bytes or fewer
+/// but doesn't match either the unlocked or never-restore pattern.
+pub(crate) fn parse_protection(data: &[u8]) -> Result<ProtectionState, FTypeError> {
+    if data.len() > 7 {
+        return Ok(ProtectionState::PasswordProtected);
+    }
+
+    let protection_fields = nom::branch::alt((
+        value(ProtectionState::Unlocked, tag(&UNLOCKED_PATTERN[..])),
+        value(ProtectionState::NeverRestore, tag(&NEVER_RESTORE_PATTERN[..])),
+    ));
+    let result: IResult<&[u8], ProtectionState> = protection_fields(data);
+
+    result
+        .map(|(_, state)| state)
+        .map_err(|_: nom::Err<nom::error::Error<&[u8]>>| {
+            FTypeError::UnrecognizedProtectionPattern(data.to_vec())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_version_reads_major_and_minor() {
+        let version = parse_version(&[0x03, 0x0C, 0x00]).unwrap();
+        assert_eq!(version, FileVersion { major_rev: 12, minor_rev: 0 });
+    }
+
+    #[test]
+    fn parse_version_rejects_truncated_stream() {
+        let err = parse_version(&[0x03, 0x0C]).unwrap_err();
+        assert!(matches!(err, FTypeError::TruncatedVersion { len: 2 }));
+    }
+
+    #[test]
+    fn parse_version_rejects_empty_stream() {
+        let err = parse_version(&[]).unwrap_err();
+        assert!(matches!(err, FTypeError::TruncatedVersion { len: 0 }));
+    }
+
+    #[test]
+    fn parse_version_rejects_oversized_stream() {
+        let err = parse_version(&[0x03, 0x0C, 0x00, 0x01]).unwrap_err();
+        assert!(matches!(err, FTypeError::UnexpectedVersionLayout { len: 4 }));
+    }
+
+    #[test]
+    fn parse_protection_recognizes_unlocked_pattern() {
+        assert_eq!(
+            parse_protection(&UNLOCKED_PATTERN).unwrap(),
+            ProtectionState::Unlocked
+        );
+    }
+
+    #[test]
+    fn parse_protection_recognizes_never_restore_pattern() {
+        assert_eq!(
+            parse_protection(&NEVER_RESTORE_PATTERN).unwrap(),
+            ProtectionState::NeverRestore
+        );
+    }
+
+    #[test]
+    fn parse_protection_recognizes_password_protected() {
+        let hashed = [0u8; 16];
+        assert_eq!(
+            parse_protection(&hashed).unwrap(),
+            ProtectionState::PasswordProtected
+        );
+    }
+
+    #[test]
+    fn parse_protection_rejects_unrecognized_seven_byte_pattern() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
+        let err = parse_protection(&bytes).unwrap_err();
+        assert!(matches!(err, FTypeError::UnrecognizedProtectionPattern(got) if got == bytes));
+    }
+
+    // Regression test for a behavior change from the old bare-bool `is_protected`: a
+    // too-short stream that matches neither pattern used to be silently treated as
+    // "unprotected" (false). It's now a hard parse error instead.
+    #[test]
+    fn parse_protection_rejects_too_short_stream_instead_of_treating_as_unlocked() {
+        let err = parse_protection(&[0x00, 0x03]).unwrap_err();
+        assert!(matches!(err, FTypeError::UnrecognizedProtectionPattern(_)));
+    }
+}
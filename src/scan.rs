@@ -0,0 +1,270 @@
+//! Scans many `FactoryTalk` View files at once and reports their version, file type, and
+//! protection state together as a single manifest, instead of callers having to zip up
+//! several parallel `Vec`s themselves.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::file_type::{classify, ensure_compound_file};
+use crate::{protection_state_from_cfb, version_from_cfb, FileType, FileVersion, ProtectionState};
+
+/// Identifies an `ab_versions` scan report on disk, before the schema version is even read.
+const SCAN_MAGIC: [u8; 4] = *b"ABVS";
+
+/// The current on-disk schema version written by [`ScanReport::write`].
+const SCAN_SCHEMA_VERSION: u64 = 1;
+
+#[derive(Error, Debug)]
+pub enum ScanError {
+    #[error("There was an error while trying to read or write the scan report")]
+    IoError(#[from] std::io::Error),
+
+    #[error("The file doesn't look like an ab_versions scan report (bad magic bytes)")]
+    NotAScanReport,
+
+    #[error("Scan report schema version {found} is newer than the {supported} this crate supports")]
+    UnsupportedSchemaVersion { found: u64, supported: u64 },
+
+    #[error("There was an error (de)serializing the scan report body")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// What `scan` found out about a single file.
+///
+/// Each field is `None` rather than the whole record failing, since a file can be, say, a
+/// valid MER with a readable version but for some reason an unreadable protection stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub path: PathBuf,
+    pub version: Option<FileVersion>,
+    pub file_type: Option<FileType>,
+    pub protection: Option<ProtectionState>,
+}
+
+/// A manifest covering every file passed to [`scan`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub records: Vec<ScanRecord>,
+}
+
+/// Writes `value` as a big-endian, minimal-length integer prefixed with its own byte
+/// length. Byte-wise comparison of the encoded form sorts the same as the numeric value,
+/// since a shorter length prefix always means a smaller number.
+fn write_ordered_varint<W: Write>(writer: &mut W, value: u64) -> std::io::Result<()> {
+    let bytes = value.to_be_bytes();
+    let trimmed = match bytes.iter().position(|&b| b != 0) {
+        Some(index) => &bytes[index..],
+        None => &bytes[7..],
+    };
+
+    writer.write_all(&[u8::try_from(trimmed.len()).expect("at most 8 bytes in a u64")])?;
+    writer.write_all(trimmed)
+}
+
+/// Reads a value written by [`write_ordered_varint`].
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error if the length prefix read from `reader` is greater than
+/// 8 — such a value could never have come from `write_ordered_varint` and would otherwise
+/// index out of bounds below.
+fn read_ordered_varint<R: Read>(reader: &mut R) -> std::io::Result<u64> {
+    let mut len_byte = [0u8; 1];
+    reader.read_exact(&mut len_byte)?;
+    let len = usize::from(len_byte[0]);
+
+    if len > 8 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("ordered varint length prefix {len} exceeds the maximum of 8"),
+        ));
+    }
+
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf[8 - len..])?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+impl ScanReport {
+    /// Writes the magic bytes, schema version, and the records themselves (as JSON) to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if writing to `writer` fails.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), ScanError> {
+        writer.write_all(&SCAN_MAGIC)?;
+        write_ordered_varint(&mut writer, SCAN_SCHEMA_VERSION)?;
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a manifest written by [`ScanReport::write`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `reader` doesn't start with the expected magic bytes, if its
+    /// schema version is newer than this crate supports, or if the body can't be parsed.
+    pub fn read<R: Read>(mut reader: R) -> Result<ScanReport, ScanError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != SCAN_MAGIC {
+            return Err(ScanError::NotAScanReport);
+        }
+
+        let schema_version = read_ordered_varint(&mut reader)?;
+        if schema_version > SCAN_SCHEMA_VERSION {
+            return Err(ScanError::UnsupportedSchemaVersion {
+                found: schema_version,
+                supported: SCAN_SCHEMA_VERSION,
+            });
+        }
+
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// A convenience export of the records as pretty-printed JSON, for human consumption
+    /// rather than round-tripping through [`ScanReport::read`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+// https://rust-lang.github.io/rust-clippy/master/index.html#missing_errors_doc
+/// Scans a batch of `FactoryTalk` View files and combines their version, file type, and
+/// protection state into a single [`ScanReport`].
+///
+/// # Arguments
+///
+/// * `files` - A slice of paths to the files to be scanned
+///
+/// # Examples
+///
+/// ```no_run
+/// use ab_versions::scan;
+/// let report = scan(&paths_to_files);
+/// ```
+pub fn scan<P: AsRef<Path> + Sync>(files: &[P]) -> ScanReport {
+    let records = files
+        .as_parallel_slice()
+        .par_iter()
+        .map(|path| scan_one(path.as_ref()))
+        .collect();
+
+    ScanReport { records }
+}
+
+/// Scans a single file, opening and walking its compound file directory only once, then
+/// reading version/type/protection off that one handle instead of three separate opens.
+fn scan_one(path: &Path) -> ScanRecord {
+    let file = ensure_compound_file(path).and_then(|()| cfb::open(path).map_err(Into::into));
+
+    let (version, file_type, protection) = match file {
+        Ok(mut file) => (
+            version_from_cfb(&mut file).ok(),
+            Some(classify(&mut file)),
+            protection_state_from_cfb(&mut file).ok(),
+        ),
+        Err(_) => (None, None, None),
+    };
+
+    ScanRecord {
+        path: path.to_path_buf(),
+        version,
+        file_type,
+        protection,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_report() -> ScanReport {
+        ScanReport {
+            records: vec![ScanRecord {
+                path: PathBuf::from("Runtime.mer"),
+                version: Some(FileVersion {
+                    major_rev: 12,
+                    minor_rev: 0,
+                }),
+                file_type: Some(FileType::MerRuntime),
+                protection: Some(ProtectionState::Unlocked),
+            }],
+        }
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let report = sample_report();
+        let mut buffer = Vec::new();
+        report.write(&mut buffer).unwrap();
+
+        let read_back = ScanReport::read(buffer.as_slice()).unwrap();
+
+        assert_eq!(read_back.records.len(), report.records.len());
+        assert_eq!(read_back.records[0].path, report.records[0].path);
+        assert_eq!(read_back.records[0].version, report.records[0].version);
+        assert_eq!(read_back.records[0].file_type, report.records[0].file_type);
+        assert_eq!(read_back.records[0].protection, report.records[0].protection);
+    }
+
+    #[test]
+    fn read_rejects_bad_magic() {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(b"NOPE");
+        write_ordered_varint(&mut buffer, SCAN_SCHEMA_VERSION).unwrap();
+        serde_json::to_writer(&mut buffer, &sample_report()).unwrap();
+
+        assert!(matches!(
+            ScanReport::read(buffer.as_slice()),
+            Err(ScanError::NotAScanReport)
+        ));
+    }
+
+    #[test]
+    fn read_rejects_newer_schema_version() {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&SCAN_MAGIC);
+        write_ordered_varint(&mut buffer, SCAN_SCHEMA_VERSION + 1).unwrap();
+        serde_json::to_writer(&mut buffer, &sample_report()).unwrap();
+
+        assert!(matches!(
+            ScanReport::read(buffer.as_slice()),
+            Err(ScanError::UnsupportedSchemaVersion { found, supported })
+                if found == SCAN_SCHEMA_VERSION + 1 && supported == SCAN_SCHEMA_VERSION
+        ));
+    }
+
+    #[test]
+    fn read_rejects_oversized_length_prefix_instead_of_panicking() {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&SCAN_MAGIC);
+        buffer.push(9); // no valid u64 encoding has a length prefix greater than 8
+
+        let err = ScanReport::read(buffer.as_slice()).unwrap_err();
+        assert!(matches!(err, ScanError::IoError(_)));
+    }
+
+    #[test]
+    fn ordered_varint_round_trips_and_preserves_order() {
+        let values = [0_u64, 1, 255, 256, u64::from(u32::MAX), u64::MAX];
+
+        let mut encoded: Vec<Vec<u8>> = Vec::new();
+        for &value in &values {
+            let mut buffer = Vec::new();
+            write_ordered_varint(&mut buffer, value).unwrap();
+            assert_eq!(read_ordered_varint(&mut buffer.as_slice()).unwrap(), value);
+            encoded.push(buffer);
+        }
+
+        assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}